@@ -0,0 +1,93 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::TempoError;
+use crate::jira::Worklog;
+
+/// FNV-1a, 64-bit. `std::collections::hash_map::DefaultHasher` is explicitly documented as
+/// unspecified and may change between Rust releases, which would silently invalidate every
+/// checkpoint on disk after a toolchain upgrade; FNV-1a is a fixed, simple algorithm so the same
+/// content always hashes the same way regardless of compiler version.
+fn fnv1a_64(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(PRIME)
+    })
+}
+
+/// A key identifying a specific upload attempt: the worklog's source id plus a hash of the
+/// fields that are actually sent to Tempo. Hashing the content (not just the id) means an
+/// interval that was edited after a failed upload is treated as new, while an unmodified one is
+/// still recognized even if the timewarrior tagging step never completed.
+fn checkpoint_key(worklog: &Worklog) -> String {
+    let content = format!(
+        "{}|{}|{}|{}",
+        worklog.issue,
+        worklog.date,
+        worklog.duration.num_seconds(),
+        worklog.description
+    );
+    format!("{}:{:x}", worklog.id, fnv1a_64(content.as_bytes()))
+}
+
+/// Tracks which worklogs have already been successfully uploaded to Tempo, so a crash between
+/// the upload and the subsequent timewarrior tagging doesn't cause a duplicate worklog on the
+/// next run. Persisted to the confy config dir, alongside the cached login `Session`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Checkpoints {
+    uploaded: HashSet<String>,
+}
+
+impl Checkpoints {
+    pub fn load() -> Result<Self, TempoError> {
+        Ok(confy::load("tempoit", Some("checkpoints"))?)
+    }
+
+    pub fn save(&self) -> Result<(), TempoError> {
+        Ok(confy::store("tempoit", Some("checkpoints"), self)?)
+    }
+
+    pub fn is_uploaded(&self, worklog: &Worklog) -> bool {
+        self.uploaded.contains(&checkpoint_key(worklog))
+    }
+
+    /// Record `worklog` as uploaded and persist immediately, so the checkpoint survives even if
+    /// the process is killed before the caller can finish tagging it in timewarrior.
+    pub fn mark_uploaded(&mut self, worklog: &Worklog) -> Result<(), TempoError> {
+        self.uploaded.insert(checkpoint_key(worklog));
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate};
+
+    use super::*;
+
+    fn worklog() -> Worklog {
+        Worklog {
+            duration: Duration::minutes(30),
+            date: NaiveDate::from_ymd(2021, 1, 1),
+            issue: "SE-1".to_owned(),
+            description: "comms".to_owned(),
+            id: "@1".to_owned(),
+        }
+    }
+
+    #[test]
+    fn checkpoint_key_is_stable() {
+        // A fixed-point regression test: if this ever changes, something about the hash
+        // algorithm changed, and every checkpoint already on disk would look "new" again.
+        assert_eq!(checkpoint_key(&worklog()), "@1:e85482d4b22edbf6");
+    }
+
+    #[test]
+    fn checkpoint_key_changes_with_content() {
+        let mut edited = worklog();
+        edited.description = "other work".to_owned();
+        assert_ne!(checkpoint_key(&worklog()), checkpoint_key(&edited));
+    }
+}
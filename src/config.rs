@@ -0,0 +1,212 @@
+use std::time::Duration;
+
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+fn deserialize_regex<'de, D>(deserializer: D) -> Result<Regex, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let regex_str = String::deserialize(deserializer)?;
+    Regex::new(&regex_str).map_err(serde::de::Error::custom)
+}
+
+fn serialize_regex<S>(re: &Regex, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(re.as_str())
+}
+
+/// Parse a duration written like `"5m"` or `"30s"` (a number followed by one of `s`/`m`/`h`/`d`).
+fn parse_duration(raw: &str) -> Result<Duration, String> {
+    let raw = raw.trim();
+    let split_at = raw
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("duration '{}' is missing a s/m/h/d suffix", raw))?;
+    let (value, suffix) = raw.split_at(split_at);
+    let value: u64 = value
+        .parse()
+        .map_err(|_| format!("duration '{}' does not start with a number", raw))?;
+    let multiplier = match suffix {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        _ => return Err(format!("duration '{}' has unknown suffix '{}'", raw, suffix)),
+    };
+    Ok(Duration::from_secs(value * multiplier))
+}
+
+/// Render a duration back as a plain number of seconds, e.g. `"300s"`.
+fn format_duration(duration: &Duration) -> String {
+    format!("{}s", duration.as_secs())
+}
+
+fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+    parse_duration(&raw).map_err(serde::de::Error::custom)
+}
+
+fn serialize_duration<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&format_duration(duration))
+}
+
+fn deserialize_option_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = Option::<String>::deserialize(deserializer)?;
+    raw.map(|raw| parse_duration(&raw).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+fn serialize_option_duration<S>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match duration {
+        Some(duration) => serializer.serialize_some(&format_duration(duration)),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// The default for `session_validity_secs`, used when loading a config written before that field
+/// existed so upgrading doesn't hard-fail on a missing field.
+fn default_session_validity_secs() -> u64 {
+    2 * 60 * 60
+}
+
+/// The default for `watch_poll_interval`, used when loading a config written before that field
+/// existed so upgrading doesn't hard-fail on a missing field.
+fn default_watch_poll_interval() -> Duration {
+    Duration::from_secs(5 * 60)
+}
+
+/// The default for `watch_max_errors_in_row`, used when loading a config written before that
+/// field existed so upgrading doesn't hard-fail on a missing field.
+fn default_watch_max_errors_in_row() -> u32 {
+    5
+}
+
+/// The default for `upload_max_retries`, used when loading a config written before that field
+/// existed so upgrading doesn't hard-fail on a missing field.
+fn default_upload_max_retries() -> u32 {
+    3
+}
+
+/// The default for `upload_max_backoff`, used when loading a config written before that field
+/// existed so upgrading doesn't hard-fail on a missing field.
+fn default_upload_max_backoff() -> Duration {
+    Duration::from_secs(60)
+}
+
+/// Which `TimeSource` implementation to read worklogs from.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeSourceKind {
+    Timewarrior,
+}
+
+/// The default for `backend`, used when loading a config written before that field existed so
+/// upgrading doesn't hard-fail on a missing field.
+fn default_backend() -> TimeSourceKind {
+    TimeSourceKind::Timewarrior
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Config {
+    pub username: String,
+    pub password: String,
+    pub base_url: String,
+    #[serde(deserialize_with = "deserialize_regex")]
+    #[serde(serialize_with = "serialize_regex")]
+    pub ticket_regex: Regex,
+
+    /// Which time tracker to pull worklogs from.
+    #[serde(default = "default_backend")]
+    pub backend: TimeSourceKind,
+
+    /// How long a cached login session is trusted before tempoit falls back to a fresh login.
+    #[serde(default = "default_session_validity_secs")]
+    pub session_validity_secs: u64,
+
+    /// How often `watch` polls timewarrior for new worklogs, e.g. `"5m"` or `"30s"`.
+    #[serde(default = "default_watch_poll_interval")]
+    #[serde(deserialize_with = "deserialize_duration")]
+    #[serde(serialize_with = "serialize_duration")]
+    pub watch_poll_interval: Duration,
+
+    /// If set, `watch` exits cleanly once it has been running for this long.
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_duration")]
+    #[serde(serialize_with = "serialize_option_duration")]
+    pub watch_max_run_duration: Option<Duration>,
+
+    /// `watch` aborts after this many consecutive cycles that failed outright.
+    #[serde(default = "default_watch_max_errors_in_row")]
+    pub watch_max_errors_in_row: u32,
+
+    /// How many times to retry a single worklog upload after a transient failure (a network error
+    /// or a 5xx response) before giving up on it. Permanent failures are never retried.
+    #[serde(default = "default_upload_max_retries")]
+    pub upload_max_retries: u32,
+
+    /// The retry backoff doubles after each attempt, capped at this duration.
+    #[serde(default = "default_upload_max_backoff")]
+    #[serde(deserialize_with = "deserialize_duration")]
+    #[serde(serialize_with = "serialize_duration")]
+    pub upload_max_backoff: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            username: "user".to_owned(),
+            password: "pass".to_owned(),
+            base_url: "https://tasks.opencraft.com".to_owned(),
+            ticket_regex: Regex::new(r"^(?i:FAL|SE|BB|OC|MNG|BIZ|ADMIN)-\d+$")
+                .expect("default regex is invalid"),
+            backend: TimeSourceKind::Timewarrior,
+            session_validity_secs: 2 * 60 * 60,
+            watch_poll_interval: Duration::from_secs(5 * 60),
+            watch_max_run_duration: None,
+            watch_max_errors_in_row: 5,
+            upload_max_retries: 3,
+            upload_max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_test() {
+        assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("5m").unwrap(), Duration::from_secs(5 * 60));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(2 * 60 * 60));
+        assert_eq!(
+            parse_duration("1d").unwrap(),
+            Duration::from_secs(24 * 60 * 60)
+        );
+        assert_eq!(parse_duration(" 5m ").unwrap(), Duration::from_secs(5 * 60));
+
+        assert!(parse_duration("5").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("5x").is_err());
+    }
+
+    #[test]
+    fn format_duration_test() {
+        assert_eq!(format_duration(&Duration::from_secs(300)), "300s");
+        assert_eq!(format_duration(&Duration::from_secs(0)), "0s");
+    }
+}
@@ -0,0 +1,92 @@
+use thiserror::Error;
+
+use crate::timew::Interval;
+
+/// All the ways tempoit can fail, from parsing timewarrior intervals through to uploading
+/// worklogs to Tempo. Kept as a single enum (rather than one per module) so that `main` can match
+/// on the variant to decide whether something is informational (e.g. an interval that's still
+/// running) or a hard failure that should affect the exit code.
+#[derive(Debug, Error)]
+pub enum TempoError {
+    /// The interval is still running (has no `end`), so there's nothing to upload yet. This is
+    /// expected during normal use and should not be treated as an error.
+    #[error("INFO( open ): {0}")]
+    OpenInterval(Interval),
+
+    /// The interval is already tagged `logged`, so it was uploaded on a previous run. This is
+    /// expected during normal use and should not be treated as an error.
+    #[error("INFO( already logged ): {0}")]
+    AlreadyLogged(Interval),
+
+    /// None of the interval's tags matched the configured ticket regex.
+    #[error("ERR( untagged ): {0}")]
+    Untagged(Interval),
+
+    /// The interval has no annotation to use as the worklog description.
+    #[error("ERR(no ann): {0}")]
+    MissingAnnotation(Interval),
+
+    /// The Jira login form was submitted but the response did not indicate success.
+    #[error("login failed")]
+    LoginFailed,
+
+    /// Fetching the remaining estimate for a ticket did not return something usable.
+    #[error("failed to fetch remaining estimate: {0}")]
+    RemainingEstimateFailed(String),
+
+    /// Tempo rejected a worklog upload; the response body is included verbatim to aid debugging.
+    #[error("upload for {issue} was rejected: {response}")]
+    UploadRejected { issue: String, response: String },
+
+    /// The cached session's cookie jar could not be (de)serialized.
+    #[error("session cookie jar error: {0}")]
+    Session(String),
+
+    /// One or more worklogs failed to upload; the individual failures were already reported.
+    #[error("upload complete with errors")]
+    UploadsFailed,
+
+    #[error(transparent)]
+    Config(#[from] confy::ConfyError),
+
+    #[error(transparent)]
+    Reqwest(#[from] reqwest::Error),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+}
+
+impl TempoError {
+    /// Whether a retry of the operation that produced this error stands a chance of succeeding,
+    /// as opposed to a permanent failure (bad credentials, a rejected upload) that will just fail
+    /// the same way again. Used to decide whether a failed upload is worth retrying with backoff.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            TempoError::Reqwest(e) => {
+                e.is_timeout() || e.is_connect() || e.status().map_or(false, |s| s.is_server_error())
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `reqwest::Error` has no public constructor, so the 5xx/timeout/connect branch can't be
+    // unit-tested here; only the permanent-failure variants are covered.
+    #[test]
+    fn permanent_failures_are_not_retryable() {
+        assert!(!TempoError::LoginFailed.is_retryable());
+        assert!(!TempoError::UploadRejected {
+            issue: "SE-1".to_owned(),
+            response: "nope".to_owned(),
+        }
+        .is_retryable());
+        assert!(!TempoError::Session("bad cookie jar".to_owned()).is_retryable());
+    }
+}
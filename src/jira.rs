@@ -1,9 +1,18 @@
 use std::fmt;
+use std::io::{stdin, stdout, Write};
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
 
 use chrono::{Duration, NaiveDate};
+use cookie_store::CookieStore;
+use reqwest_cookie_store::CookieStoreMutex;
 use serde::{Deserialize, Serialize, Serializer};
 
+use crate::error::TempoError;
+use crate::session::Session;
+
 const LOGIN_ENDPOINT: &str = "/rest/gadget/1.0/login";
+const TFA_ENDPOINT: &str = "/rest/gadget/1.0/login/tfa";
 const WORKLOGS_ENDPOINT: &str = "/rest/tempo-rest/1.0/worklogs/";
 
 pub(crate) fn serialize_date<S>(date: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error>
@@ -81,6 +90,35 @@ struct LoginResponse {
     login_succeeded: bool,
 }
 
+impl LoginResponse {
+    /// Classify the reply as either a completed login or a TFA challenge that must be answered
+    /// before the session is usable. Anything else is a hard login failure.
+    fn classify(self) -> Result<SessionResponse, TempoError> {
+        if self.is_elevated_security_check_shown {
+            Ok(SessionResponse::Tfa(TfaChallenge))
+        } else if self.login_succeeded {
+            Ok(SessionResponse::Full)
+        } else {
+            Err(TempoError::LoginFailed)
+        }
+    }
+}
+
+/// The two shapes a login reply can take: `Full`, a fully authenticated session, or `Tfa`, a
+/// challenge that must be answered with a one-time password before one is granted.
+enum SessionResponse {
+    Full,
+    Tfa(TfaChallenge),
+}
+
+/// Placeholder carrying no data of its own; its presence is the prompt to ask the user for an OTP.
+struct TfaChallenge;
+
+#[derive(Serialize, Debug)]
+struct TfaForm<'a> {
+    otp_token: &'a str,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 enum WorklogActionType {
@@ -110,14 +148,18 @@ pub struct JiraClient {
 impl JiraClient {
     /// Build a new client given the `base_url` of the Jira instance. The `base_url` must be
     /// without a trailing slash. For example: `"https://tasks.opencraft.com"`.
-    /// This function will attempt to login. If successful, it will return a `JiraClient` with a
-    /// logged in session ready to make api calls. If not, it will return an `Err`.
+    ///
+    /// If a cached session exists for this `base_url`/`username` and is younger than
+    /// `session_validity`, it is reused and no login request is made. Otherwise this performs a
+    /// fresh form login (answering a TFA challenge interactively if one is shown) and caches the
+    /// resulting session for next time.
     ///
     /// ```rust
+    /// # use std::time::Duration;
     /// # use tempoit::jira::JiraClient;
     /// # #[tokio::main]
     /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let client = JiraClient::new("https://example.com", "my_user", "hunter2").await;
+    /// let client = JiraClient::new("https://example.com", "my_user", "hunter2", Duration::from_secs(7200)).await;
     /// # assert_eq!(client.is_err(), true);
     /// # Ok(())
     /// # }
@@ -126,12 +168,49 @@ impl JiraClient {
         base_url: &str,
         username: &str,
         password: &str,
-    ) -> Result<Self, Box<dyn std::error::Error>> {
+        session_validity: StdDuration,
+    ) -> Result<Self, TempoError> {
+        let cached = Session::load()?;
+        if cached.is_valid_for(base_url, username, session_validity) {
+            let cookie_store = Arc::new(CookieStoreMutex::new(cached.cookie_store()?));
+            let client = reqwest::Client::builder()
+                .cookie_provider(cookie_store)
+                .build()?;
+            return Ok(Self {
+                client,
+                username: username.to_owned(),
+                base_url: base_url.to_owned(),
+            });
+        }
+
+        let cookie_store = Arc::new(CookieStoreMutex::new(CookieStore::default()));
+        let client = reqwest::Client::builder()
+            .cookie_provider(Arc::clone(&cookie_store))
+            .build()?;
+
+        Self::login(&client, base_url, username, password).await?;
+
+        let session = Session::new(base_url, username, &cookie_store.lock().unwrap())?;
+        session.save()?;
+
+        Ok(Self {
+            client,
+            username: username.to_owned(),
+            base_url: base_url.to_owned(),
+        })
+    }
+
+    /// Submit the login form, answering a TFA challenge if the reply shows one.
+    async fn login(
+        client: &reqwest::Client,
+        base_url: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<(), TempoError> {
         let login_form = LoginForm {
             os_username: username,
             os_password: password,
         };
-        let client = reqwest::Client::builder().cookie_store(true).build()?;
         let res = client
             .post(&format!("{}{}", base_url, LOGIN_ENDPOINT))
             .form(&login_form)
@@ -139,14 +218,30 @@ impl JiraClient {
             .await?
             .error_for_status()?;
         let data: LoginResponse = res.json().await?;
-        if data.login_succeeded {
-            Ok(Self {
-                client,
-                username: username.to_owned(),
-                base_url: base_url.to_owned(),
-            })
-        } else {
-            Err(std::io::Error::new(std::io::ErrorKind::Other, "login failed").into())
+
+        match data.classify()? {
+            SessionResponse::Full => Ok(()),
+            SessionResponse::Tfa(_challenge) => {
+                print!(":: Two-factor authentication required, enter OTP: ");
+                stdout().flush()?;
+                let mut otp = String::new();
+                stdin().read_line(&mut otp)?;
+                let tfa_form = TfaForm {
+                    otp_token: otp.trim(),
+                };
+
+                let res = client
+                    .post(&format!("{}{}", base_url, TFA_ENDPOINT))
+                    .form(&tfa_form)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                let data: LoginResponse = res.json().await?;
+                match data.classify()? {
+                    SessionResponse::Full => Ok(()),
+                    SessionResponse::Tfa(_) => Err(TempoError::LoginFailed),
+                }
+            }
         }
     }
 
@@ -156,10 +251,7 @@ impl JiraClient {
     /// must be added to the call to add a worklog, otherwise the remaining estimate is not
     /// updated. We also can't calculate it ourselves, because we don't know an api endpoint to get
     /// the ticket info. (TODO: this would be a good addition)
-    async fn get_remaining_estimate(
-        &self,
-        worklog: &Worklog,
-    ) -> Result<String, Box<dyn std::error::Error>> {
+    async fn get_remaining_estimate(&self, worklog: &Worklog) -> Result<String, TempoError> {
         // This calculates the time remaining to set if 10m work to be added to SE-2424
         // https://tasks.opencraft.com/rest/tempo-rest/1.0/worklogs/remainingEstimate/calculate/SE-2552/2019-05-29/2019-05-29/3m
         let estimate_url = format!(
@@ -176,13 +268,17 @@ impl JiraClient {
             .send()
             .await?
             .error_for_status()?;
-        Ok(estimate_response.text().await?)
+        let estimate = estimate_response.text().await?;
+        if estimate.trim().is_empty() {
+            return Err(TempoError::RemainingEstimateFailed(estimate));
+        }
+        Ok(estimate)
     }
 
     /// Upload a worklog to Tempo. Note that this is _not_ idempotent; If called twice, this will
     /// add two identical worklogs to tempo. There is also no known way to retrieve the id of the
     /// worklog once uploaded, so it is impossible to find to modify or delete programmatically.
-    pub async fn add_worklog(&self, worklog: &Worklog) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn add_worklog(&self, worklog: &Worklog) -> Result<(), TempoError> {
         let form = WorklogUpdateForm {
             action_type: WorklogActionType::LogTime,
             ansidate: worklog.date,
@@ -205,11 +301,10 @@ impl JiraClient {
         let response_text = worklog_response.text().await?;
 
         if response_text.find("valid=\"true\"").is_none() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("Err: {}", response_text),
-            )
-            .into());
+            return Err(TempoError::UploadRejected {
+                issue: worklog.issue.clone(),
+                response: response_text,
+            });
         }
 
         Ok(())
@@ -0,0 +1,8 @@
+pub mod checkpoint;
+pub mod config;
+pub mod error;
+pub mod jira;
+pub mod report;
+pub mod session;
+pub mod time_source;
+pub mod timew;
@@ -1,130 +1,387 @@
 use std::io::{stdin, stdout, Write};
+use std::time::{Duration as StdDuration, Instant};
 
 use chrono::Duration;
 use confy;
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use structopt::StructOpt;
-use regex::Regex;
 
-use tempoit::jira::{duration_to_jira, JiraClient};
+use tempoit::checkpoint::Checkpoints;
+use tempoit::config::{Config, TimeSourceKind};
+use tempoit::error::TempoError;
+use tempoit::jira::{duration_to_jira, JiraClient, Worklog};
+use tempoit::report::{classify_error, UploadOutcome, UploadReport, WorklogReport};
+use tempoit::time_source::TimeSource;
 use tempoit::timew::TimewClient;
 
-fn deserialize_regex<'de, D>(deserializer: D) -> Result<Regex, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let regex_str = String::deserialize(deserializer)?;
-    Regex::new(&regex_str).map_err(serde::de::Error::custom)
-}
-
-fn serialize_regex<S>(re: &Regex, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    serializer.serialize_str(re.as_str())
+/// Build the configured `TimeSource` backend. Timewarrior is the only one that exists today, but
+/// this is the one place `main` needs to touch to support another tracker.
+fn build_time_source(cfg: &Config) -> Box<dyn TimeSource> {
+    match cfg.backend {
+        TimeSourceKind::Timewarrior => Box::new(TimewClient::new(cfg.ticket_regex.clone())),
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Config {
-    username: String,
-    password: String,
-    base_url: String,
-    #[serde(deserialize_with = "deserialize_regex")]
-    #[serde(serialize_with = "serialize_regex")]
-    ticket_regex: Regex,
-}
+/// The backoff before the first retry; it doubles after each further attempt, up to
+/// `cfg.upload_max_backoff`.
+const RETRY_BASE_BACKOFF: StdDuration = StdDuration::from_secs(5);
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            username: "user".to_owned(),
-            password: "pass".to_owned(),
-            base_url: "https://tasks.opencraft.com".to_owned(),
-            ticket_regex: Regex::new(r"^(?i:FAL|SE|BB|OC|MNG|BIZ|ADMIN)-\d+$").expect("default regex is invalid"),
+/// Upload `worklog`, retrying transient failures (network errors, 5xx responses) with exponential
+/// backoff up to `cfg.upload_max_retries` times. Permanent failures (rejected login, rejected
+/// upload) are returned immediately without retrying. Progress is appended to the in-flight
+/// `":: Uploading ... "` line unless `quiet` is set.
+async fn upload_with_retry(
+    jira_client: &JiraClient,
+    worklog: &Worklog,
+    cfg: &Config,
+    quiet: bool,
+) -> Result<(), TempoError> {
+    let mut attempt = 0;
+    loop {
+        match jira_client.add_worklog(worklog).await {
+            Ok(()) => return Ok(()),
+            Err(e) if e.is_retryable() && attempt < cfg.upload_max_retries => {
+                // `attempt` is derived from the user-configurable `upload_max_retries`, so don't
+                // assume it stays small enough for a plain `1 << attempt` to avoid overflow.
+                let multiplier = 1u32.checked_shl(attempt).unwrap_or(u32::MAX);
+                let backoff = RETRY_BASE_BACKOFF
+                    .saturating_mul(multiplier)
+                    .min(cfg.upload_max_backoff);
+                attempt += 1;
+                if !quiet {
+                    print!(
+                        "retry {}/{} in {}s... ",
+                        attempt,
+                        cfg.upload_max_retries,
+                        backoff.as_secs()
+                    );
+                    stdout().flush()?;
+                }
+                tokio::time::sleep(backoff).await;
+            }
+            Err(e) => return Err(e),
         }
     }
 }
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "tempoit", about = "Upload worklogs to jira from timew export")]
-struct Opt {}
+struct Opt {
+    #[structopt(subcommand)]
+    command: Option<Command>,
+
+    /// Skip the interactive confirmation prompt and upload immediately.
+    #[structopt(long)]
+    yes: bool,
+
+    /// Parse and validate worklogs, print the planned uploads and total time, but don't upload
+    /// anything.
+    #[structopt(long)]
+    dry_run: bool,
+
+    /// Emit a machine-readable JSON report to stdout instead of the interactive prose output.
+    #[structopt(long)]
+    json: bool,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Run continuously, uploading newly logged intervals on a schedule instead of exiting after
+    /// one pass.
+    Watch,
+}
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let _opt = Opt::from_args();
+async fn main() -> Result<(), TempoError> {
+    let opt = Opt::from_args();
     let cfg: Config = confy::load("tempoit")?;
-    let logs_client = TimewClient::new(cfg.ticket_regex);
 
-    let parsed_intervals = logs_client.get_worklogs()?;
+    match opt.command {
+        Some(Command::Watch) => watch(&cfg).await,
+        None => upload_once(&cfg, &opt).await,
+    }
+}
+
+fn total_duration<'a>(worklogs: impl IntoIterator<Item = &'a Worklog>) -> Duration {
+    worklogs
+        .into_iter()
+        .map(|x| x.duration)
+        .fold(Duration::seconds(0), |acc, x| acc + x)
+}
+
+/// Parse timewarrior's export, then either report what would be uploaded (`--dry-run`) or
+/// confirm with the user (unless `--yes`) and upload. `--json` replaces all of the above prose
+/// with a single machine-readable report on stdout.
+async fn upload_once(cfg: &Config, opt: &Opt) -> Result<(), TempoError> {
+    let logs_client = build_time_source(cfg);
+
+    let parsed_intervals = logs_client.get_worklogs().await?;
 
-    // Check for errors. Display and exit if found any.
+    // Split into worklogs ready to upload and skipped/errored entries. Open/already-logged
+    // intervals are expected and purely informational; anything else is a hard error that
+    // should affect the exit code, but we still keep processing the rest rather than aborting.
     let mut worklogs = vec![];
+    let mut reports = vec![];
+    let mut had_hard_errors = false;
     for maybe_worklog in parsed_intervals {
         match maybe_worklog {
-            Err(s) => {
-                println!("{}", s);
-            }
-            Ok(w) => {
-                worklogs.push(w);
+            Ok(w) => worklogs.push(w),
+            Err(e) => {
+                let (outcome, interval) = classify_error(&e);
+                if !matches!(
+                    e,
+                    TempoError::OpenInterval(_) | TempoError::AlreadyLogged(_)
+                ) {
+                    had_hard_errors = true;
+                }
+                if !opt.json {
+                    match e {
+                        TempoError::OpenInterval(_) | TempoError::AlreadyLogged(_) => {
+                            println!("{}", e)
+                        }
+                        _ => eprintln!("{}", e),
+                    }
+                }
+                if let Some(interval) = interval {
+                    reports.push(WorklogReport::for_skipped_interval(interval, outcome));
+                }
             }
         }
     }
 
     if worklogs.len() == 0 {
-        println!(":: No worklogs to upload.");
-        return Ok(());
+        if opt.json {
+            print_report(reports, Duration::seconds(0))?;
+        } else {
+            println!(":: No worklogs to upload.");
+        }
+        return finish(had_hard_errors);
     }
 
-    println!(":: Ready to upload worklogs:");
-    for worklog in worklogs.iter() {
-        println!("   {}", worklog);
+    // Loaded up front (rather than just before the real upload) so `--dry-run` sees the same
+    // checkpoint state a real run would and doesn't claim already-uploaded worklogs as new.
+    let mut checkpoints = Checkpoints::load()?;
+
+    if opt.dry_run {
+        let new_worklogs: Vec<&Worklog> = worklogs
+            .iter()
+            .filter(|w| !checkpoints.is_uploaded(w))
+            .collect();
+        for worklog in worklogs.iter() {
+            let outcome = if checkpoints.is_uploaded(worklog) {
+                UploadOutcome::AlreadyUploaded
+            } else {
+                UploadOutcome::Planned
+            };
+            reports.push(WorklogReport::for_worklog(worklog, outcome));
+        }
+        let new_total = total_duration(new_worklogs.iter().copied());
+        if opt.json {
+            print_report(reports, new_total)?;
+        } else {
+            println!(":: Planned uploads (dry run):");
+            for worklog in new_worklogs.iter() {
+                println!("   {}", worklog);
+            }
+            println!(":: Total time: {}", duration_to_jira(&new_total));
+        }
+        return finish(had_hard_errors);
     }
-    println!(
-        ":: Total time: {}",
-        duration_to_jira(
-            &worklogs
-                .iter()
-                .map(|x| x.duration)
-                .fold(Duration::seconds(0), |acc, x| acc + x.clone())
-        )
-    );
-    print!(":: Confirm upload [y/N] ");
-    stdout().flush()?;
-
-    let mut response = String::new();
-    stdin().read_line(&mut response)?;
-    let response = response.trim().to_lowercase();
-    if response != "y" {
-        println!(":: Canceled by user, Aborting.");
-        return Ok(());
+
+    if !opt.json {
+        println!(":: Ready to upload worklogs:");
+        for worklog in worklogs.iter() {
+            println!("   {}", worklog);
+        }
+        println!(":: Total time: {}", duration_to_jira(&total_duration(&worklogs)));
     }
 
-    let jira_client = JiraClient::new(&cfg.base_url, &cfg.username, &cfg.password).await?;
+    // `--json` is meant for non-interactive use, so it implies `--yes`: there's no terminal to
+    // prompt on the other end, and blocking on stdin there would just read EOF and silently abort
+    // without ever printing the report the caller is expecting to parse.
+    if !opt.yes && !opt.json {
+        print!(":: Confirm upload [y/N] ");
+        stdout().flush()?;
+
+        let mut response = String::new();
+        stdin().read_line(&mut response)?;
+        let response = response.trim().to_lowercase();
+        if response != "y" {
+            println!(":: Canceled by user, Aborting.");
+            return finish(had_hard_errors);
+        }
+    }
+
+    let jira_client = JiraClient::new(
+        &cfg.base_url,
+        &cfg.username,
+        &cfg.password,
+        StdDuration::from_secs(cfg.session_validity_secs),
+    )
+    .await?;
 
-    let mut failed_uploads = vec![];
     for worklog in worklogs.iter() {
-        print!(":: Uploading {}... ", worklog);
-        match jira_client.add_worklog(worklog).await {
+        if checkpoints.is_uploaded(worklog) {
+            if !opt.json {
+                println!(":: {} already uploaded, completing tag...", worklog);
+            }
+            reports.push(WorklogReport::for_worklog(
+                worklog,
+                UploadOutcome::AlreadyUploaded,
+            ));
+            logs_client.record_success(&worklog.id).await?;
+            continue;
+        }
+
+        if !opt.json {
+            print!(":: Uploading {}... ", worklog);
+        }
+        match upload_with_retry(&jira_client, worklog, cfg, opt.json).await {
             Err(e) => {
-                println!("FAIL");
-                println!("{}", e);
-                failed_uploads.push(worklog);
-                logs_client.record_fail(&worklog.id)?;
+                if !opt.json {
+                    println!("FAIL");
+                    println!("{}", e);
+                }
+                reports.push(WorklogReport::for_worklog(
+                    worklog,
+                    UploadOutcome::Failed {
+                        message: e.to_string(),
+                    },
+                ));
+                had_hard_errors = true;
+                logs_client.record_fail(&worklog.id).await?;
             }
             Ok(_) => {
-                println!("SUCCESS");
-                logs_client.record_success(&worklog.id)?;
+                if !opt.json {
+                    println!("SUCCESS");
+                }
+                checkpoints.mark_uploaded(worklog)?;
+                reports.push(WorklogReport::for_worklog(worklog, UploadOutcome::Uploaded));
+                logs_client.record_success(&worklog.id).await?;
             }
         }
     }
 
-    if failed_uploads.len() > 0 {
-        println!(":: Some worklogs failed to upload. Please try again:");
-        for worklog in failed_uploads {
-            println!("   {}", worklog);
-        }
-        return Err("Upload complete with errors.".into());
+    if opt.json {
+        print_report(reports, total_duration(&worklogs))?;
+    } else if had_hard_errors {
+        println!(":: Some worklogs failed to upload. Please try again.");
     }
 
+    finish(had_hard_errors)
+}
+
+fn print_report(worklogs: Vec<WorklogReport>, total: Duration) -> Result<(), TempoError> {
+    let report = UploadReport {
+        worklogs,
+        total_duration: duration_to_jira(&total),
+    };
+    println!("{}", serde_json::to_string_pretty(&report)?);
     Ok(())
 }
+
+fn finish(had_hard_errors: bool) -> Result<(), TempoError> {
+    if had_hard_errors {
+        return Err(TempoError::UploadsFailed);
+    }
+    Ok(())
+}
+
+/// Run tempoit as a long-lived daemon: every `cfg.watch_poll_interval` check timewarrior for new
+/// worklogs and upload them non-interactively, until `cfg.watch_max_run_duration` elapses (if
+/// set) or `cfg.watch_max_errors_in_row` consecutive cycles fail outright.
+async fn watch(cfg: &Config) -> Result<(), TempoError> {
+    let logs_client = build_time_source(cfg);
+    let jira_client = JiraClient::new(
+        &cfg.base_url,
+        &cfg.username,
+        &cfg.password,
+        StdDuration::from_secs(cfg.session_validity_secs),
+    )
+    .await?;
+
+    let mut checkpoints = Checkpoints::load()?;
+    let started_at = Instant::now();
+    let mut errors_in_row = 0u32;
+
+    loop {
+        if let Some(max_run) = cfg.watch_max_run_duration {
+            if started_at.elapsed() >= max_run {
+                println!(":: Reached configured max run duration, stopping.");
+                return Ok(());
+            }
+        }
+
+        match run_watch_cycle(cfg, &logs_client, &jira_client, &mut checkpoints).await {
+            Ok((uploaded, failed)) => {
+                errors_in_row = 0;
+                if uploaded + failed > 0 {
+                    println!(":: Cycle complete: {} uploaded, {} failed.", uploaded, failed);
+                }
+            }
+            Err(e) => {
+                errors_in_row += 1;
+                eprintln!(
+                    ":: Cycle failed ({}/{} in a row): {}",
+                    errors_in_row, cfg.watch_max_errors_in_row, e
+                );
+                if errors_in_row >= cfg.watch_max_errors_in_row {
+                    return Err(e);
+                }
+            }
+        }
+
+        tokio::time::sleep(cfg.watch_poll_interval).await;
+    }
+}
+
+/// Upload every new, fully-tagged worklog once. Returns the counts of successful and failed
+/// uploads for the caller to log; a worklog already uploaded on an earlier run and only re-tagged
+/// now counts as successful. Only failures in talking to timewarrior itself or in saving the
+/// checkpoint file are propagated, since a single rejected worklog shouldn't stop the rest of the
+/// cycle.
+async fn run_watch_cycle(
+    cfg: &Config,
+    logs_client: &dyn TimeSource,
+    jira_client: &JiraClient,
+    checkpoints: &mut Checkpoints,
+) -> Result<(usize, usize), TempoError> {
+    let parsed_intervals = logs_client.get_worklogs().await?;
+
+    let mut uploaded = 0;
+    let mut failed = 0;
+    for maybe_worklog in parsed_intervals {
+        let worklog = match maybe_worklog {
+            Err(TempoError::OpenInterval(_)) | Err(TempoError::AlreadyLogged(_)) => continue,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+            Ok(w) => w,
+        };
+
+        if checkpoints.is_uploaded(&worklog) {
+            println!(":: {} already uploaded, completing tag...", worklog);
+            logs_client.record_success(&worklog.id).await?;
+            uploaded += 1;
+            continue;
+        }
+
+        print!(":: Uploading {}... ", worklog);
+        match upload_with_retry(jira_client, &worklog, cfg, false).await {
+            Ok(_) => {
+                println!("SUCCESS");
+                checkpoints.mark_uploaded(&worklog)?;
+                logs_client.record_success(&worklog.id).await?;
+                uploaded += 1;
+            }
+            Err(e) => {
+                println!("FAIL");
+                println!("{}", e);
+                logs_client.record_fail(&worklog.id).await?;
+                failed += 1;
+            }
+        }
+    }
+
+    Ok((uploaded, failed))
+}
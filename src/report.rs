@@ -0,0 +1,132 @@
+use serde::Serialize;
+
+use crate::error::TempoError;
+use crate::jira::{duration_to_jira, Worklog};
+use crate::timew::Interval;
+
+/// What happened to a single worklog candidate. Used to build the `--json` report so scripts can
+/// consume upload outcomes reliably instead of scraping the interactive output.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadOutcome {
+    Uploaded,
+    /// Already uploaded on a previous run (per the checkpoint file), so not uploaded again; outside
+    /// `--dry-run`, only the timewarrior tag was (re)applied this time.
+    AlreadyUploaded,
+    Planned,
+    SkippedOpen,
+    SkippedLogged,
+    Untagged,
+    MissingAnnotation,
+    Failed { message: String },
+}
+
+/// A single line of the `--json` report.
+#[derive(Debug, Serialize)]
+pub struct WorklogReport {
+    pub id: String,
+    pub issue: Option<String>,
+    pub date: Option<String>,
+    pub duration: Option<String>,
+    pub outcome: UploadOutcome,
+}
+
+impl WorklogReport {
+    pub fn for_worklog(worklog: &Worklog, outcome: UploadOutcome) -> Self {
+        Self {
+            id: worklog.id.clone(),
+            issue: Some(worklog.issue.clone()),
+            date: Some(worklog.date.to_string()),
+            duration: Some(duration_to_jira(&worklog.duration)),
+            outcome,
+        }
+    }
+
+    pub fn for_skipped_interval(interval: &Interval, outcome: UploadOutcome) -> Self {
+        Self {
+            id: interval.id().to_owned(),
+            issue: None,
+            date: interval.date().map(|d| d.to_string()),
+            duration: interval.duration().map(|d| duration_to_jira(&d)),
+            outcome,
+        }
+    }
+}
+
+/// The full `--json` report: one entry per candidate worklog, plus the aggregate uploadable time.
+#[derive(Debug, Serialize)]
+pub struct UploadReport {
+    pub worklogs: Vec<WorklogReport>,
+    pub total_duration: String,
+}
+
+/// Classify a `parse_interval` error into the outcome (and underlying interval, if any) it
+/// represents, for reporting purposes.
+pub fn classify_error(error: &TempoError) -> (UploadOutcome, Option<&Interval>) {
+    match error {
+        TempoError::OpenInterval(iv) => (UploadOutcome::SkippedOpen, Some(iv)),
+        TempoError::AlreadyLogged(iv) => (UploadOutcome::SkippedLogged, Some(iv)),
+        TempoError::Untagged(iv) => (UploadOutcome::Untagged, Some(iv)),
+        TempoError::MissingAnnotation(iv) => (UploadOutcome::MissingAnnotation, Some(iv)),
+        other => (
+            UploadOutcome::Failed {
+                message: other.to_string(),
+            },
+            None,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interval(tags: &str, end: &str, annotation: &str) -> Interval {
+        let json = format!(
+            r#"{{"id":1,"start":"20210101T000000Z","end":{end},"tags":[{tags}],"annotation":{annotation}}}"#,
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn classify_error_open_interval() {
+        let iv = interval("\"SE-1\"", "null", "\"work\"");
+        let (outcome, classified_iv) = classify_error(&TempoError::OpenInterval(iv));
+        assert!(matches!(outcome, UploadOutcome::SkippedOpen));
+        assert!(classified_iv.is_some());
+    }
+
+    #[test]
+    fn classify_error_already_logged() {
+        let iv = interval("\"logged\"", "\"20210101T010000Z\"", "\"work\"");
+        let (outcome, classified_iv) = classify_error(&TempoError::AlreadyLogged(iv));
+        assert!(matches!(outcome, UploadOutcome::SkippedLogged));
+        assert!(classified_iv.is_some());
+    }
+
+    #[test]
+    fn classify_error_untagged() {
+        let iv = interval("", "\"20210101T010000Z\"", "\"work\"");
+        let (outcome, classified_iv) = classify_error(&TempoError::Untagged(iv));
+        assert!(matches!(outcome, UploadOutcome::Untagged));
+        assert!(classified_iv.is_some());
+    }
+
+    #[test]
+    fn classify_error_missing_annotation() {
+        let iv = interval("\"SE-1\"", "\"20210101T010000Z\"", "null");
+        let (outcome, classified_iv) = classify_error(&TempoError::MissingAnnotation(iv));
+        assert!(matches!(outcome, UploadOutcome::MissingAnnotation));
+        assert!(classified_iv.is_some());
+    }
+
+    #[test]
+    fn classify_error_other_becomes_failed_with_message_and_no_interval() {
+        let (outcome, classified_iv) = classify_error(&TempoError::LoginFailed);
+        match outcome {
+            UploadOutcome::Failed { message } => assert_eq!(message, "login failed"),
+            other => panic!("expected Failed, got {:?}", other),
+        }
+        assert!(classified_iv.is_none());
+    }
+}
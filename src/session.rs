@@ -0,0 +1,105 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use cookie_store::CookieStore;
+use serde::{Deserialize, Serialize};
+
+use crate::error::TempoError;
+
+/// A cached Jira login, persisted to the confy config dir so tempoit doesn't have to perform a
+/// fresh form login (and potentially a TFA challenge) on every invocation. The cookie jar is kept
+/// as its own JSON blob, since `CookieStore` only knows how to (de)serialize itself as JSON, while
+/// the rest of the session is plain TOML like the main config.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Session {
+    base_url: String,
+    username: String,
+    created_at: u64,
+    cookies_json: String,
+}
+
+impl Session {
+    /// Build a session from a freshly logged-in cookie jar, stamped with the current time.
+    pub fn new(base_url: &str, username: &str, cookie_store: &CookieStore) -> Result<Self, TempoError> {
+        let mut cookies_json = Vec::new();
+        cookie_store
+            .save_json(&mut cookies_json)
+            .map_err(|e| TempoError::Session(e.to_string()))?;
+
+        Ok(Self {
+            base_url: base_url.to_owned(),
+            username: username.to_owned(),
+            created_at: now_unix(),
+            cookies_json: String::from_utf8(cookies_json)
+                .map_err(|e| TempoError::Session(e.to_string()))?,
+        })
+    }
+
+    /// Load the last cached session, if any. Returns an empty (always-invalid) session when none
+    /// has been saved yet, so callers can treat "absent" and "expired" the same way.
+    pub fn load() -> Result<Self, TempoError> {
+        Ok(confy::load("tempoit", Some("session"))?)
+    }
+
+    /// Persist this session so the next run can reuse it.
+    pub fn save(&self) -> Result<(), TempoError> {
+        Ok(confy::store("tempoit", Some("session"), self)?)
+    }
+
+    /// Whether this session belongs to `base_url`/`username` and is still within `validity`.
+    pub fn is_valid_for(&self, base_url: &str, username: &str, validity: Duration) -> bool {
+        self.base_url == base_url
+            && self.username == username
+            && now_unix().saturating_sub(self.created_at) < validity.as_secs()
+    }
+
+    /// Rebuild the cookie jar this session cached.
+    pub fn cookie_store(&self) -> Result<CookieStore, TempoError> {
+        CookieStore::load_json(self.cookies_json.as_bytes())
+            .map_err(|e| TempoError::Session(e.to_string()))
+    }
+}
+
+fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock is before the unix epoch")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(base_url: &str, username: &str, created_at: u64) -> Session {
+        Session {
+            base_url: base_url.to_owned(),
+            username: username.to_owned(),
+            created_at,
+            cookies_json: String::new(),
+        }
+    }
+
+    #[test]
+    fn is_valid_for_matching_session_within_validity() {
+        let s = session("https://example.com", "alice", now_unix());
+        assert!(s.is_valid_for("https://example.com", "alice", Duration::from_secs(100)));
+    }
+
+    #[test]
+    fn is_valid_for_expired_session() {
+        let s = session("https://example.com", "alice", now_unix().saturating_sub(1000));
+        assert!(!s.is_valid_for("https://example.com", "alice", Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn is_valid_for_base_url_mismatch() {
+        let s = session("https://example.com", "alice", now_unix());
+        assert!(!s.is_valid_for("https://other.com", "alice", Duration::from_secs(100)));
+    }
+
+    #[test]
+    fn is_valid_for_username_mismatch() {
+        let s = session("https://example.com", "alice", now_unix());
+        assert!(!s.is_valid_for("https://example.com", "bob", Duration::from_secs(100)));
+    }
+}
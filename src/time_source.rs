@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+
+use crate::error::TempoError;
+use crate::jira::Worklog;
+
+/// A source of worklogs to upload to Tempo. `TimewClient` is the only implementation today, but
+/// this lets tempoit point at another time tracker (e.g. a CSV export) without touching the Jira
+/// upload path in `main`.
+#[async_trait]
+pub trait TimeSource {
+    /// Fetch and parse all candidate worklogs. Each item is `Ok` when fully parseable, or an
+    /// error describing why that particular entry was skipped or rejected.
+    async fn get_worklogs(&self) -> Result<Vec<Result<Worklog, TempoError>>, TempoError>;
+
+    /// Mark a worklog (by its source-specific id) as successfully uploaded.
+    async fn record_success(&self, id: &str) -> Result<(), TempoError>;
+
+    /// Mark a worklog (by its source-specific id) as failed to upload.
+    async fn record_fail(&self, id: &str) -> Result<(), TempoError>;
+}
@@ -1,13 +1,15 @@
-use std::error::Error;
 use std::fmt;
 use std::process::Command;
 use std::str;
 
+use async_trait::async_trait;
 use chrono::{DateTime, Local, NaiveDateTime, Utc};
 use regex::Regex;
 use serde::{Deserialize, Deserializer};
 
+use crate::error::TempoError;
 use crate::jira::Worklog;
+use crate::time_source::TimeSource;
 
 fn deserialize_id<'de, D>(deserializer: D) -> Result<String, D::Error>
 where
@@ -42,8 +44,8 @@ where
 }
 
 // All datetimes output from timew export are in utc.
-#[derive(Debug, Deserialize)]
-struct Interval {
+#[derive(Debug, Clone, Deserialize)]
+pub struct Interval {
     #[serde(deserialize_with = "deserialize_id")]
     id: String,
     #[serde(deserialize_with = "deserialize_datetime")]
@@ -56,6 +58,25 @@ struct Interval {
     annotation: Option<String>,
 }
 
+impl Interval {
+    /// The timewarrior id for this interval, e.g. `"@123"`.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The date this interval should be filed under, in the same timezone-local terms
+    /// `parse_interval` uses, or `None` while the interval is still open.
+    pub fn date(&self) -> Option<chrono::NaiveDate> {
+        self.end
+            .map(|end| end.with_timezone(&Local).date().naive_local())
+    }
+
+    /// How long this interval ran for, or `None` while it is still open.
+    pub fn duration(&self) -> Option<chrono::Duration> {
+        self.end.map(|end| end - self.start)
+    }
+}
+
 impl fmt::Display for Interval {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let date = self.end.unwrap_or(self.start).date().format("%Y-%m-%d");
@@ -83,23 +104,25 @@ impl fmt::Display for Interval {
     }
 }
 
-fn parse_interval(interval: &Interval) -> Result<Worklog, String> {
+fn parse_interval(interval: &Interval, ticket_regex: &Regex) -> Result<Worklog, TempoError> {
+    if interval.tags.iter().any(|t| t == "logged") {
+        return Err(TempoError::AlreadyLogged(interval.clone()));
+    }
+
     let end = match interval.end {
         Some(end) => end,
         None => {
-            return Err(format!("INFO( open ): {}", interval));
+            return Err(TempoError::OpenInterval(interval.clone()));
         }
     };
 
     let duration = end - interval.start;
     let date = end.with_timezone(&Local).date().naive_local();
 
-    // TODO:  make this regex configurable from config file
-    let re = Regex::new(r"^(?i:SE|BB|OC|MNG|BIZ|ADMIN)-\d+$").expect("regex invalid");
-    let issue = match interval.tags.iter().find(|x| re.is_match(x)) {
+    let issue = match interval.tags.iter().find(|x| ticket_regex.is_match(x)) {
         Some(issue) => issue.to_uppercase(),
         None => {
-            return Err(format!("ERR( untagged ): {}", interval));
+            return Err(TempoError::Untagged(interval.clone()));
         }
     };
 
@@ -107,7 +130,7 @@ fn parse_interval(interval: &Interval) -> Result<Worklog, String> {
 
     let description = match interval.annotation.clone() {
         None => {
-            return Err(format!("ERR(no ann): {}", interval));
+            return Err(TempoError::MissingAnnotation(interval.clone()));
         }
         Some(ann) => ann,
     };
@@ -121,42 +144,54 @@ fn parse_interval(interval: &Interval) -> Result<Worklog, String> {
     });
 }
 
-type ClientResult<T> = Result<T, Box<dyn Error>>;
+type ClientResult<T> = Result<T, TempoError>;
 
-pub struct TimewClient {}
+pub struct TimewClient {
+    ticket_regex: Regex,
+}
 
 impl TimewClient {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(ticket_regex: Regex) -> Self {
+        Self { ticket_regex }
     }
+}
 
-    pub fn get_worklogs(&self) -> ClientResult<Vec<Result<Worklog, String>>> {
+#[async_trait]
+impl TimeSource for TimewClient {
+    async fn get_worklogs(&self) -> ClientResult<Vec<Result<Worklog, TempoError>>> {
         // TODO: make this command configurable
         let proc = Command::new("timew")
             .args(&["export", "oc", "log"])
             .output()?;
-        let export_contents = str::from_utf8(&proc.stdout)?;
+        let export_contents = str::from_utf8(&proc.stdout)
+            .map_err(|e| TempoError::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))?;
         let intervals: Vec<Interval> = serde_json::from_str(export_contents)?;
 
-        Ok(intervals.iter().map(parse_interval).collect())
+        Ok(intervals
+            .iter()
+            .map(|i| parse_interval(i, &self.ticket_regex))
+            .collect())
     }
 
-    pub fn record_success(&self, id: &str) -> ClientResult<()> {
+    async fn record_success(&self, id: &str) -> ClientResult<()> {
         run("timew", &["tag", id, "logged"])?;
         run("timew", &["untag", id, "log", "logfail"])
     }
 
-    pub fn record_fail(&self, id: &str) -> ClientResult<()> {
+    async fn record_fail(&self, id: &str) -> ClientResult<()> {
         run("timew", &["tag", id, "logfail"])
     }
 }
 
 /// Helper function to spawn and run a command, returning an error if did not exit cleanly.
-pub fn run(cmd: &str, args: &[&str]) -> Result<(), Box<dyn Error>> {
+pub fn run(cmd: &str, args: &[&str]) -> Result<(), TempoError> {
     println!("RUN {} {:?}", cmd, args);
     let status = Command::new(cmd).args(args).status()?;
     match status.success() {
         true => Ok(()),
-        false => Err(format!("Command exited with {}", status).into()),
+        false => Err(TempoError::Io(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            format!("Command exited with {}", status),
+        ))),
     }
 }